@@ -1,13 +1,27 @@
 use std::{
 	borrow::Cow,
+	cell::RefCell,
 	collections::{HashMap, HashSet},
+	rc::Rc,
+	time::Duration,
 };
 
-use itertools::Itertools;
+use chrono::DateTime;
 use regex_lite::Regex;
-use scraper::{Html, Selector};
+use rss::{Channel, ChannelBuilder, ItemBuilder};
+use scraper::{ElementRef, Html, Selector};
+use serde_json::{json, Value};
+use tokio::sync::Semaphore;
 use url::Position;
-use worker::*;
+use worker::{kv::KvStore, *};
+
+const USER_AGENT: &str = "site-scraper/1.0 (+https://github.com/sokorototo/site-scraper)";
+
+// robots.txt User-agent lines name this short product token, not the full USER_AGENT header
+const ROBOTS_PRODUCT_TOKEN: &str = "site-scraper";
+
+const GLOBAL_CONCURRENCY: usize = 6;
+const PER_HOST_CONCURRENCY: usize = 2;
 
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -16,6 +30,23 @@ struct SiteDefinition {
 	follow_links: Option<String>,
 	max_depth: Option<u32>,
 	searches: Vec<Search>,
+	#[serde(default)]
+	output: Output,
+	crawl_delay: Option<f64>,
+	max_age: Option<u64>,
+	target: Option<String>,
+	max_pages: Option<u32>,
+	max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Output {
+	#[default]
+	Json,
+	Rss,
+	Diff,
+	Backlinks,
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -23,17 +54,329 @@ struct SiteDefinition {
 struct Search {
 	selector: String,
 	attributes: Vec<String>,
+	#[serde(default)]
+	feed_role: Option<FeedRole>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FeedRole {
+	title: Option<String>,
+	link: Option<String>,
+	description: Option<String>,
+	pub_date: Option<String>,
 }
 
 async fn load_site(link: String) -> Result<(String, String)> {
-	let fetch = Fetch::Url(Url::parse(&link)?);
-	let mut res = fetch.send().await?;
+	let mut headers = Headers::new();
+	headers.set("User-Agent", USER_AGENT)?;
+
+	let mut init = RequestInit::new();
+	init.with_method(Method::Get).with_headers(headers);
+
+	let request = Request::new_with_init(&link, &init)?;
+	let mut res = Fetch::Request(request).send().await?;
 	res.text().await.map(|r| (r, link))
 }
 
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+struct CachedPage {
+	body: String,
+	etag: Option<String>,
+	last_modified: Option<String>,
+	cached_at_ms: f64,
+}
+
+async fn load_site_cached(link: String, kv: &KvStore, max_age: Option<Duration>) -> Result<(String, String)> {
+	let cache_key = normalize_url(&link).unwrap_or_else(|| link.clone());
+	let cached = kv.get(&cache_key).json::<CachedPage>().await?;
+
+	if let (Some(cached), Some(max_age)) = (&cached, max_age) {
+		let age_ms = Date::now().as_millis() as f64 - cached.cached_at_ms;
+		if age_ms < max_age.as_millis() as f64 {
+			return Ok((cached.body.clone(), link));
+		}
+	}
+
+	let mut headers = Headers::new();
+	headers.set("User-Agent", USER_AGENT)?;
+	if let Some(cached) = &cached {
+		if let Some(etag) = &cached.etag {
+			headers.set("If-None-Match", etag)?;
+		}
+		if let Some(last_modified) = &cached.last_modified {
+			headers.set("If-Modified-Since", last_modified)?;
+		}
+	}
+
+	let mut init = RequestInit::new();
+	init.with_method(Method::Get).with_headers(headers);
+
+	let request = Request::new_with_init(&link, &init)?;
+	let mut res = Fetch::Request(request).send().await?;
+
+	if res.status_code() == 304 {
+		if let Some(cached) = cached {
+			return Ok((cached.body, link));
+		}
+	}
+
+	let body = res.text().await?;
+	let entry = CachedPage {
+		body: body.clone(),
+		etag: res.headers().get("ETag")?,
+		last_modified: res.headers().get("Last-Modified")?,
+		cached_at_ms: Date::now().as_millis() as f64,
+	};
+
+	let mut put = kv.put(&cache_key, &entry)?;
+	if let Some(max_age) = max_age {
+		put = put.expiration_ttl(max_age.as_secs().max(60));
+	}
+	put.execute().await?;
+
+	Ok((body, link))
+}
+
+#[derive(Debug, Default, Clone)]
+struct RobotsRules {
+	disallow: Vec<String>,
+	crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+	fn allows(&self, path: &str) -> bool {
+		!self.disallow.iter().any(|prefix| path.starts_with(prefix.as_str()))
+	}
+}
+
+#[derive(Debug, Default)]
+struct RobotsGroup {
+	agents: Vec<String>,
+	disallow: Vec<String>,
+	crawl_delay: Option<Duration>,
+}
+
+// Per RFC 9309, a declared agent token matches ours as a case-insensitive prefix
+fn robots_agent_matches(agent: &str, product_token: &str) -> bool {
+	agent != "*" && product_token.to_lowercase().starts_with(&agent.to_lowercase())
+}
+
+fn parse_robots(body: &str, product_token: &str) -> RobotsRules {
+	// Buffer every group and choose between them at the end, so a group's position in the
+	// file (wildcard-first or wildcard-last) can't change which one wins
+	let mut groups = Vec::<RobotsGroup>::new();
+
+	for line in body.lines() {
+		let line = line.split('#').next().unwrap_or("").trim();
+		let Some((key, value)) = line.split_once(':') else { continue };
+		let (key, value) = (key.trim().to_lowercase(), value.trim());
+
+		match key.as_str() {
+			"user-agent" => match groups.last_mut() {
+				Some(group) if group.disallow.is_empty() && group.crawl_delay.is_none() => group.agents.push(value.to_owned()),
+				_ => groups.push(RobotsGroup { agents: vec![value.to_owned()], ..Default::default() }),
+			},
+			"disallow" if !value.is_empty() => {
+				if let Some(group) = groups.last_mut() {
+					group.disallow.push(value.to_owned());
+				}
+			}
+			"crawl-delay" => {
+				if let (Some(group), Ok(seconds)) = (groups.last_mut(), value.parse::<f64>()) {
+					group.crawl_delay = Some(Duration::from_secs_f64(seconds));
+				}
+			}
+			_ => {}
+		}
+	}
+
+	let chosen = groups
+		.iter()
+		.find(|group| group.agents.iter().any(|agent| robots_agent_matches(agent, product_token)))
+		.or_else(|| groups.iter().find(|group| group.agents.iter().any(|agent| agent == "*")));
+
+	match chosen {
+		Some(group) => RobotsRules {
+			disallow: group.disallow.clone(),
+			crawl_delay: group.crawl_delay,
+		},
+		None => RobotsRules::default(),
+	}
+}
+
+async fn fetch_robots(origin: &str) -> RobotsRules {
+	let robots_url = format!("{origin}/robots.txt");
+	match load_site(robots_url).await {
+		Ok((body, _)) => parse_robots(&body, ROBOTS_PRODUCT_TOKEN),
+		Err(_) => RobotsRules::default(),
+	}
+}
+
+#[cfg(test)]
+mod robots_tests {
+	use super::*;
+
+	#[test]
+	fn specific_group_then_wildcard_uses_only_specific_rules() {
+		let body = "User-agent: site-scraper\nDisallow: /private\n\nUser-agent: *\nDisallow: /\n";
+		let rules = parse_robots(body, ROBOTS_PRODUCT_TOKEN);
+		assert_eq!(rules.disallow, vec!["/private".to_owned()]);
+	}
+
+	#[test]
+	fn wildcard_then_specific_group_uses_only_specific_rules() {
+		let body = "User-agent: *\nDisallow: /\n\nUser-agent: site-scraper\nDisallow: /private\n";
+		let rules = parse_robots(body, ROBOTS_PRODUCT_TOKEN);
+		assert_eq!(rules.disallow, vec!["/private".to_owned()]);
+	}
+
+	#[test]
+	fn falls_back_to_wildcard_when_no_specific_group_matches() {
+		let body = "User-agent: some-other-bot\nDisallow: /private\n\nUser-agent: *\nDisallow: /\n";
+		let rules = parse_robots(body, ROBOTS_PRODUCT_TOKEN);
+		assert_eq!(rules.disallow, vec!["/".to_owned()]);
+	}
+}
+
+struct CrawlScheduler {
+	global_permits: Rc<Semaphore>,
+	host_permits: RefCell<HashMap<String, Rc<Semaphore>>>,
+	host_last_fetch_ms: RefCell<HashMap<String, f64>>,
+	robots: RefCell<HashMap<String, RobotsRules>>,
+	default_crawl_delay: Option<Duration>,
+	kv: KvStore,
+	max_age: Option<Duration>,
+}
+
+impl CrawlScheduler {
+	fn new(default_crawl_delay: Option<Duration>, kv: KvStore, max_age: Option<Duration>) -> Self {
+		CrawlScheduler {
+			global_permits: Rc::new(Semaphore::new(GLOBAL_CONCURRENCY)),
+			host_permits: RefCell::new(HashMap::new()),
+			host_last_fetch_ms: RefCell::new(HashMap::new()),
+			robots: RefCell::new(HashMap::new()),
+			default_crawl_delay,
+			kv,
+			max_age,
+		}
+	}
+
+	async fn ensure_robots(&self, origin: &str) {
+		if self.robots.borrow().contains_key(origin) {
+			return;
+		}
+
+		let mut rules = fetch_robots(origin).await;
+		if rules.crawl_delay.is_none() {
+			rules.crawl_delay = self.default_crawl_delay;
+		}
+
+		self.robots.borrow_mut().insert(origin.to_owned(), rules);
+	}
+
+	fn is_allowed(&self, origin: &str, path: &str) -> bool {
+		self.robots.borrow().get(origin).map_or(true, |rules| rules.allows(path))
+	}
+
+	fn host_semaphore(&self, origin: &str) -> Rc<Semaphore> {
+		self.host_permits.borrow_mut().entry(origin.to_owned()).or_insert_with(|| Rc::new(Semaphore::new(PER_HOST_CONCURRENCY))).clone()
+	}
+
+	async fn wait_for_turn(&self, origin: &str) {
+		let crawl_delay = self.robots.borrow().get(origin).and_then(|rules| rules.crawl_delay);
+
+		if let Some(crawl_delay) = crawl_delay {
+			let last_fetch_ms = self.host_last_fetch_ms.borrow().get(origin).copied();
+			if let Some(last_fetch_ms) = last_fetch_ms {
+				let elapsed_ms = Date::now().as_millis() as f64 - last_fetch_ms;
+				let remaining_ms = crawl_delay.as_millis() as f64 - elapsed_ms;
+				if remaining_ms > 0.0 {
+					Delay::from(Duration::from_millis(remaining_ms as u64)).await;
+				}
+			}
+		}
+
+		self.host_last_fetch_ms.borrow_mut().insert(origin.to_owned(), Date::now().as_millis() as f64);
+	}
+
+	// Expects `ensure_robots` to have run for `site`'s host; robots.txt `Disallow` rules are not checked here
+	async fn fetch(&self, site: String) -> Result<(String, String)> {
+		let origin = Url::parse(&site)?.origin().ascii_serialization();
+		let host_sem = self.host_semaphore(&origin);
+
+		let _global_permit = self.global_permits.acquire().await.map_err(|e| e.to_string())?;
+		let _host_permit = host_sem.acquire().await.map_err(|e| e.to_string())?;
+
+		self.wait_for_turn(&origin).await;
+		load_site_cached(site, &self.kv, self.max_age).await
+	}
+}
+
+fn extract_attribute<'result>(element: &ElementRef<'result>, attribute: &str, base: Option<&Url>) -> Option<Cow<'result, str>> {
+	match attribute {
+		"#TextContent" => Some(Cow::Owned(element.text().collect::<String>())),
+		"#HtmlContent" => Some(Cow::Owned(element.html())),
+		"#InnerHtml" => Some(Cow::Owned(element.inner_html())),
+		"#Html2Text" => Some(Cow::Owned(nanohtml2text::html2text(&element.inner_html()))),
+		"#Microformats" => serde_json::to_string(&extract_mf2_item(element, base)).ok().map(Cow::Owned),
+		attribute => element.value().attr(attribute).map(Cow::Borrowed),
+	}
+}
+
+fn classes<'result>(element: &ElementRef<'result>) -> impl Iterator<Item = &'result str> {
+	element.value().attr("class").into_iter().flat_map(|c| c.split_whitespace())
+}
+
+fn extract_mf2_item(element: &ElementRef, base: Option<&Url>) -> Value {
+	let types = classes(element).filter(|c| c.starts_with("h-")).map(str::to_owned).collect::<Vec<_>>();
+
+	let mut properties = serde_json::Map::new();
+	for child in element.children().filter_map(ElementRef::wrap) {
+		collect_mf2_properties(&child, base, &mut properties);
+	}
+
+	json!({ "type": types, "properties": properties })
+}
+
+fn collect_mf2_properties(element: &ElementRef, base: Option<&Url>, properties: &mut serde_json::Map<String, Value>) {
+	let is_item = classes(element).any(|c| c.starts_with("h-"));
+	let mut matched_property = false;
+
+	for class in classes(element) {
+		let Some((prefix @ ("p" | "u" | "e" | "dt"), name)) = class.split_once('-') else { continue };
+		matched_property = true;
+
+		// An h-* item nests under the property it was found on instead of being flattened
+		let value = if is_item {
+			extract_mf2_item(element, base)
+		} else {
+			match prefix {
+				"u" => {
+					let href = element.value().attr("href").or_else(|| element.value().attr("src")).unwrap_or_default();
+					let resolved = base.and_then(|b| b.join(href).ok()).map(|u| u.to_string()).unwrap_or_else(|| href.to_owned());
+					json!(resolved)
+				}
+				"e" => json!(element.inner_html()),
+				"dt" => json!(element.value().attr("datetime").map(str::to_owned).unwrap_or_else(|| element.text().collect::<String>())),
+				_ => json!(element.text().collect::<String>()),
+			}
+		};
+
+		properties.entry(format!("{prefix}-{name}")).or_insert_with(|| json!([])).as_array_mut().unwrap().push(value);
+	}
+
+	if !matched_property {
+		for child in element.children().filter_map(ElementRef::wrap) {
+			collect_mf2_properties(&child, base, properties);
+		}
+	}
+}
+
 fn resolve_selectors<'name, 'result>(
 	parsed: &'result Html,
 	selectors: &[(&'name str, Selector, &[String])],
+	base: Option<&Url>,
 	results: &mut HashMap<&'name str, HashMap<&str, HashSet<Cow<'result, str>>>>,
 ) {
 	for (selector_name, selector, attributes) in selectors {
@@ -42,41 +385,186 @@ fn resolve_selectors<'name, 'result>(
 
 			for attribute in attributes.iter() {
 				let attribute_set = selector_group.get_mut(attribute.as_str()).unwrap();
-				match attribute.as_str() {
-					"#TextContent" => {
-						let text = element.text().collect::<String>();
-						attribute_set.insert(Cow::Owned(text));
-					}
-					"#HtmlContent" => {
-						let html = element.html();
-						attribute_set.insert(Cow::Owned(html));
-					}
-					"#InnerHtml" => {
-						let inner_html = element.inner_html();
-						attribute_set.insert(Cow::Owned(inner_html));
-					}
-					"#Html2Text" => {
-						let inner_html = element.inner_html();
-						let text = nanohtml2text::html2text(&inner_html);
-						attribute_set.insert(Cow::Owned(text));
-					}
-					attribute => {
-						if let Some(value) = element.value().attr(attribute) {
-							attribute_set.insert(Cow::Borrowed(value));
-						}
-					}
+				if let Some(value) = extract_attribute(&element, attribute, base) {
+					attribute_set.insert(value);
 				}
 			}
 		}
 	}
 }
 
+fn collect_feed_items(parsed: &Html, base: Option<&Url>, feed_searches: &[(&Selector, &FeedRole)], items: &mut Vec<rss::Item>) {
+	for (selector, role) in feed_searches {
+		for element in parsed.select(selector) {
+			let title = role.title.as_deref().and_then(|a| extract_attribute(&element, a, base));
+			let description = role.description.as_deref().and_then(|a| extract_attribute(&element, a, base));
+
+			let link = role.link.as_deref().and_then(|a| extract_attribute(&element, a, base)).map(|link| match base.and_then(|b| b.join(&link).ok()) {
+				Some(resolved) => resolved.to_string(),
+				None => link.into_owned(),
+			});
+
+			let pub_date = role.pub_date.as_deref().and_then(|a| extract_attribute(&element, a, base)).map(|date| {
+				let trimmed = date.trim();
+				DateTime::parse_from_rfc2822(trimmed)
+					.or_else(|_| DateTime::parse_from_rfc3339(trimmed))
+					.map(|parsed| parsed.to_rfc2822())
+					.unwrap_or_else(|_| date.into_owned())
+			});
+
+			let item = ItemBuilder::default()
+				.title(title.map(Cow::into_owned))
+				.link(link)
+				.description(description.map(Cow::into_owned))
+				.pub_date(pub_date)
+				.build();
+
+			items.push(item);
+		}
+	}
+}
+
+fn build_feed(root_url: &str, items: Vec<rss::Item>) -> Channel {
+	ChannelBuilder::default()
+		.title(root_url.to_owned())
+		.link(root_url.to_owned())
+		.description(format!("Feed generated from {root_url} by site-scraper"))
+		.items(items)
+		.build()
+}
+
 fn normalize_url(url: &str) -> Option<String> {
 	let url = Url::parse(url).ok()?;
 	let homepage = &url[..Position::BeforeQuery];
 	Url::parse(homepage).ok().map(|u| u.as_str().to_owned())
 }
 
+#[derive(Debug, Default, serde::Serialize)]
+struct AttributeDiff {
+	added: Vec<String>,
+	removed: Vec<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	patch: Option<String>,
+}
+
+fn diff_cache_key(url: &str, searches: &[Search]) -> String {
+	use std::hash::{Hash, Hasher};
+
+	let mut hasher = std::collections::hash_map::DefaultHasher::new();
+	url.hash(&mut hasher);
+	for Search { selector, attributes, .. } in searches {
+		selector.hash(&mut hasher);
+		attributes.hash(&mut hasher);
+	}
+
+	format!("diff:{:x}", hasher.finish())
+}
+
+fn unified_line_diff(old: &str, new: &str) -> Option<String> {
+	let old_lines = old.lines().collect::<Vec<_>>();
+	let new_lines = new.lines().collect::<Vec<_>>();
+
+	if old_lines == new_lines {
+		return None;
+	}
+
+	let (n, m) = (old_lines.len(), new_lines.len());
+	let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+	for i in (0..n).rev() {
+		for j in (0..m).rev() {
+			lcs[i][j] = if old_lines[i] == new_lines[j] { lcs[i + 1][j + 1] + 1 } else { lcs[i + 1][j].max(lcs[i][j + 1]) };
+		}
+	}
+
+	let mut hunks = Vec::new();
+	let (mut i, mut j) = (0, 0);
+	while i < n || j < m {
+		if i < n && j < m && old_lines[i] == new_lines[j] {
+			hunks.push(format!(" {}", old_lines[i]));
+			i += 1;
+			j += 1;
+		} else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+			hunks.push(format!("-{}", old_lines[i]));
+			i += 1;
+		} else {
+			hunks.push(format!("+{}", new_lines[j]));
+			j += 1;
+		}
+	}
+
+	Some(hunks.join("\n"))
+}
+
+#[cfg(test)]
+mod unified_line_diff_tests {
+	use super::*;
+
+	#[test]
+	fn identical_text_has_no_diff() {
+		assert_eq!(unified_line_diff("a\nb\nc", "a\nb\nc"), None);
+	}
+
+	#[test]
+	fn empty_old_is_all_additions() {
+		assert_eq!(unified_line_diff("", "a\nb"), Some("+a\n+b".to_owned()));
+	}
+
+	#[test]
+	fn empty_new_is_all_removals() {
+		assert_eq!(unified_line_diff("a\nb", ""), Some("-a\n-b".to_owned()));
+	}
+
+	#[test]
+	fn inserted_line_keeps_surrounding_context() {
+		assert_eq!(unified_line_diff("a\nc", "a\nb\nc"), Some(" a\n+b\n c".to_owned()));
+	}
+
+	#[test]
+	fn removed_line_keeps_surrounding_context() {
+		assert_eq!(unified_line_diff("a\nb\nc", "a\nc"), Some(" a\n-b\n c".to_owned()));
+	}
+
+	#[test]
+	fn replaced_line_emits_removal_and_addition() {
+		assert_eq!(unified_line_diff("a\nb\nc", "a\nx\nc"), Some(" a\n-b\n+x\n c".to_owned()));
+	}
+}
+
+#[cfg(test)]
+mod mf2_tests {
+	use super::*;
+
+	#[test]
+	fn extracts_nested_h_card_with_typed_properties() {
+		let html = Html::parse_fragment(
+			r#"<div class="h-card">
+				<span class="p-name">Alice</span>
+				<a class="u-url" href="/alice"></a>
+				<time class="dt-bday" datetime="1990-01-01"></time>
+				<div class="e-note">Hello <b>world</b></div>
+				<div class="p-author h-card">
+					<span class="p-name">Bob</span>
+				</div>
+			</div>"#,
+		);
+		let selector = Selector::parse(".h-card").unwrap();
+		let root = html.select(&selector).next().unwrap();
+		let base = Url::parse("https://example.com/").unwrap();
+
+		let item = extract_mf2_item(&root, Some(&base));
+
+		assert_eq!(item["type"], json!(["h-card"]));
+		assert_eq!(item["properties"]["p-name"], json!(["Alice"]));
+		assert_eq!(item["properties"]["u-url"], json!(["https://example.com/alice"]));
+		assert_eq!(item["properties"]["dt-bday"], json!(["1990-01-01"]));
+		assert!(item["properties"]["e-note"][0].as_str().unwrap().contains("world"));
+
+		let author = &item["properties"]["p-author"][0];
+		assert_eq!(author["type"], json!(["h-card"]));
+		assert_eq!(author["properties"]["p-name"], json!(["Bob"]));
+	}
+}
+
 #[event(fetch)]
 pub async fn main(req: Request, env: Env, _: worker::Context) -> Result<Response> {
 	console_error_panic_hook::set_once();
@@ -84,24 +572,46 @@ pub async fn main(req: Request, env: Env, _: worker::Context) -> Result<Response
 	// Environment bindings like KV Stores, Durable Objects, Secrets, and Variables.
 	Router::new()
 		.get("/", |_, _| Response::ok(concat!(env!("CARGO_PKG_NAME"), " v", env!("CARGO_PKG_VERSION"))))
-		.post_async("/scrape", |mut req, _| async move {
+		.post_async("/scrape", |mut req, ctx| async move {
 			let SiteDefinition {
 				url,
 				follow_links,
 				max_depth,
 				searches,
+				output,
+				crawl_delay,
+				max_age,
+				target,
+				max_pages,
+				max_bytes,
 			} = req.json::<SiteDefinition>().await?;
 
+			let page_cache = ctx.kv("PAGE_CACHE")?;
+			let diff_cache = ctx.kv("DIFF_CACHE")?;
+
+			let normalized_target = match output {
+				Output::Backlinks => Some(target.as_deref().and_then(normalize_url).ok_or("Backlinks mode requires a valid target URL")?),
+				_ => None,
+			};
+			let mut backlinks = Vec::<String>::new();
+
 			// Initialize results map
 			let mut results = searches
 				.iter()
-				.map(|Search { selector, attributes }| (selector.as_str(), attributes.iter().map(|a| (a.as_str(), HashSet::new())).collect::<HashMap<_, _>>()))
+				.map(|Search { selector, attributes, .. }| (selector.as_str(), attributes.iter().map(|a| (a.as_str(), HashSet::new())).collect::<HashMap<_, _>>()))
 				.collect();
 
 			// Parse selectors
 			let selectors = searches
 				.iter()
-				.map(|Search { selector, attributes }| (selector.as_str(), Selector::parse(&selector).unwrap(), attributes.as_slice()))
+				.map(|Search { selector, attributes, .. }| (selector.as_str(), Selector::parse(&selector).unwrap(), attributes.as_slice()))
+				.collect::<Vec<_>>();
+
+			// Pair up parsed selectors with the searches that declare a feed role, for `output: "rss"`
+			let feed_searches = searches
+				.iter()
+				.zip(selectors.iter())
+				.filter_map(|(search, (_, selector, _))| search.feed_role.as_ref().map(|role| (selector, role)))
 				.collect::<Vec<_>>();
 
 			// Queue tasks
@@ -116,57 +626,175 @@ pub async fn main(req: Request, env: Env, _: worker::Context) -> Result<Response
 
 			let mut visited = HashSet::<Cow<str>>::new();
 			let mut current_depth = 0u32;
-			let mut document_cache = vec![];
+			let mut feed_items = Vec::new();
+
+			let mut pages_fetched = 0u32;
+			let mut bytes_fetched = 0u64;
+			let mut truncated = false;
+
+			let scheduler = CrawlScheduler::new(crawl_delay.map(Duration::from_secs_f64), page_cache, max_age.map(Duration::from_secs));
 
 			// Process tasks
-			loop {
+			'crawl: loop {
 				let mut _temp = HashSet::new();
 				if pending_sites.is_empty() || current_depth > max_depth.unwrap_or(0) {
 					break;
 				}
 
-				let queue = pending_sites.into_iter().map(|site| load_site(site)).chunks(6);
-				for chunk in queue.into_iter() {
-					for site in futures::future::join_all(chunk).await {
-						let (site_data, site) = site?;
-						let parsed = Html::parse_document(&site_data);
-						let homepage = Url::parse(&site).unwrap();
-
-						// Explore and Enqueue links
-						let new_links = parsed
-							.select(&links_selector)
-							.filter_map(|element| element.value().attr("href"))
-							.filter_map(|link| match link.get(..1) {
-								Some(c) => match c {
-									"/" => {
-										let formatted = homepage.join(link).unwrap();
-										Some(formatted.to_string())
-									}
-									"#" => None,
-									_ => Some(link.to_owned()),
-								},
-								None => None,
-							})
-							.filter_map(|l| normalize_url(&l))
-							.filter(|link| links_regex.as_ref().map_or(false, |w| w.is_match(link)))
-							.filter(|link| !visited.contains(&Cow::Borrowed(link.as_str())));
-
-						_temp.extend(new_links);
-
-						// Cache parsed documents for later processing
-						document_cache.push(parsed);
-						visited.insert(Cow::Owned(site));
+				// Cap this round's fetches to the remaining page budget, if any
+				if let Some(max_pages) = max_pages {
+					let remaining = max_pages.saturating_sub(pages_fetched) as usize;
+					if remaining == 0 {
+						truncated = true;
+						break 'crawl;
+					}
+					if pending_sites.len() > remaining {
+						truncated = true;
+						pending_sites = pending_sites.into_iter().take(remaining).collect();
 					}
 				}
 
+				// Fetch robots.txt for any hosts we haven't contacted yet before this round's requests
+				for site in &pending_sites {
+					if let Ok(origin) = Url::parse(site).map(|u| u.origin().ascii_serialization()) {
+						scheduler.ensure_robots(&origin).await;
+					}
+				}
+
+				let fetches = pending_sites.into_iter().map(|site| scheduler.fetch(site));
+				for site in futures::future::join_all(fetches).await {
+					let (site_data, site) = site?;
+					pages_fetched += 1;
+					bytes_fetched += site_data.len() as u64;
+
+					let parsed = Html::parse_document(&site_data);
+					let base = Url::parse(&site).ok();
+
+					// Extract this page's results immediately so `parsed` can be dropped once we're
+					// done with it, instead of accumulating every page in memory
+					resolve_selectors(&parsed, selectors.as_slice(), base.as_ref(), &mut results);
+					collect_feed_items(&parsed, base.as_ref(), feed_searches.as_slice(), &mut feed_items);
+
+					// Resolve every link on the page against the page's URL and normalize it;
+					// fragment-only links are skipped
+					let normalized_links = parsed
+						.select(&links_selector)
+						.filter_map(|element| element.value().attr("href"))
+						.filter_map(|link| match link.get(..1) {
+							Some(c) => match c {
+								"#" => None,
+								_ => base.as_ref().and_then(|b| b.join(link).ok()).map(|formatted| formatted.to_string()),
+							},
+							None => None,
+						})
+						.filter_map(|l| normalize_url(&l))
+						.collect::<Vec<_>>();
+
+					// In backlinks mode, record this page if it links to the target
+					if let Some(target) = &normalized_target {
+						if normalized_links.contains(target) {
+							backlinks.push(site.clone());
+						}
+					}
+
+					// Candidate links to follow: matching the follow pattern and not yet visited
+					let candidate_links = normalized_links.into_iter().filter(|link| links_regex.as_ref().map_or(false, |w| w.is_match(link))).filter(|link| !visited.contains(&Cow::Borrowed(link.as_str()))).collect::<Vec<_>>();
+
+					// Enqueue links, skipping any whose host's robots.txt disallows their path, unless
+					// we've already blown the byte budget and are about to stop the crawl anyway
+					let over_byte_budget = max_bytes.is_some_and(|max_bytes| bytes_fetched >= max_bytes);
+					if !over_byte_budget {
+						for link in candidate_links {
+							let Ok(parsed_link) = Url::parse(&link) else { continue };
+							let origin = parsed_link.origin().ascii_serialization();
+
+							scheduler.ensure_robots(&origin).await;
+							if scheduler.is_allowed(&origin, parsed_link.path()) {
+								_temp.insert(link);
+							}
+						}
+					} else if !candidate_links.is_empty() {
+						truncated = true;
+					}
+
+					visited.insert(Cow::Owned(site));
+					// `parsed` is dropped here at the end of the loop body
+				}
+
+				if max_bytes.is_some_and(|max_bytes| bytes_fetched >= max_bytes) {
+					truncated = true;
+					break 'crawl;
+				}
+
 				// drain temp into pending_sites
 				pending_sites = _temp;
 				current_depth += 1;
 			}
 
-			// Populate results
-			document_cache.iter().for_each(|doc| resolve_selectors(doc, selectors.as_slice(), &mut results));
-			Response::from_json(&results)
+			match output {
+				Output::Json => {
+					let mut headers = Headers::new();
+					headers.set("X-Truncated", &truncated.to_string())?;
+					Response::from_json(&results).map(|res| res.with_headers(headers))
+				}
+				Output::Rss => {
+					let channel = build_feed(url.as_str(), feed_items);
+
+					let mut headers = Headers::new();
+					headers.set("content-type", "application/rss+xml")?;
+					headers.set("X-Truncated", &truncated.to_string())?;
+					Response::ok(channel.to_string()).map(|res| res.with_headers(headers))
+				}
+				Output::Diff => {
+					let cache_key = diff_cache_key(url.as_str(), &searches);
+					let previous = diff_cache.get(&cache_key).json::<HashMap<String, HashMap<String, HashSet<String>>>>().await?.unwrap_or_default();
+
+					let mut diff = HashMap::<String, HashMap<String, AttributeDiff>>::new();
+					let mut snapshot = HashMap::<String, HashMap<String, HashSet<String>>>::new();
+
+					for (selector_name, attribute_sets) in &results {
+						let prev_selector = previous.get(*selector_name);
+						let mut selector_diff = HashMap::new();
+						let mut selector_snapshot = HashMap::new();
+
+						for (attribute, new_set) in attribute_sets {
+							let new_owned = new_set.iter().map(|v| v.to_string()).collect::<HashSet<_>>();
+							let empty = HashSet::new();
+							let old_set = prev_selector.and_then(|p| p.get(*attribute)).unwrap_or(&empty);
+
+							let mut added = new_owned.difference(old_set).cloned().collect::<Vec<_>>();
+							added.sort();
+							let mut removed = old_set.difference(&new_owned).cloned().collect::<Vec<_>>();
+							removed.sort();
+
+							// Only one value changed on each side, so there's no ambiguity about which
+							// old value the new one replaced; with more than one it's not possible to
+							// tell which removed value maps to which added value, so omit the patch
+							let patch = match (attribute, removed.as_slice(), added.as_slice()) {
+								(&("#TextContent" | "#Html2Text"), [old], [new]) => unified_line_diff(old, new),
+								_ => None,
+							};
+
+							selector_diff.insert(attribute.to_string(), AttributeDiff { added, removed, patch });
+							selector_snapshot.insert(attribute.to_string(), new_owned);
+						}
+
+						diff.insert(selector_name.to_string(), selector_diff);
+						snapshot.insert(selector_name.to_string(), selector_snapshot);
+					}
+
+					diff_cache.put(&cache_key, &snapshot)?.execute().await?;
+
+					let mut headers = Headers::new();
+					headers.set("X-Truncated", &truncated.to_string())?;
+					Response::from_json(&diff).map(|res| res.with_headers(headers))
+				}
+				Output::Backlinks => {
+					let mut headers = Headers::new();
+					headers.set("X-Truncated", &truncated.to_string())?;
+					Response::from_json(&backlinks).map(|res| res.with_headers(headers))
+				}
+			}
 		})
 		.run(req, env)
 		.await